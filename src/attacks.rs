@@ -0,0 +1,183 @@
+use std::sync::OnceLock;
+
+use crate::{Color, PieceKind};
+
+// Ray directions, ordered so the first four increase the 0..64 square index as they walk
+// outward (toward higher rows/columns) and the last four decrease it.
+const NORTH: usize = 0;
+const EAST: usize = 1;
+const NORTH_EAST: usize = 2;
+const NORTH_WEST: usize = 3;
+const SOUTH: usize = 4;
+const WEST: usize = 5;
+const SOUTH_EAST: usize = 6;
+const SOUTH_WEST: usize = 7;
+
+const RAY_DELTAS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (0, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+const KNIGHT_DELTAS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (-1, 2),
+    (-2, 1),
+    (1, -2),
+    (2, -1),
+    (-1, -2),
+    (-2, -1),
+];
+
+const KING_DELTAS: [(i8, i8); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+struct Tables {
+    rays: [[u64; 64]; 8],
+    knight: [u64; 64],
+    king: [u64; 64],
+    pawn: [[u64; 64]; 2],
+}
+
+fn in_bounds(row: i8, col: i8) -> bool {
+    row >= 0 && row < 8 && col >= 0 && col < 8
+}
+
+fn square_of(row: i8, col: i8) -> usize {
+    (row * 8 + col) as usize
+}
+
+fn jump_table(deltas: &[(i8, i8); 8]) -> [u64; 64] {
+    let mut table = [0u64; 64];
+    for sq in 0..64 {
+        let row = (sq / 8) as i8;
+        let col = (sq % 8) as i8;
+        for (d_row, d_col) in deltas.iter() {
+            let (r, c) = (row + d_row, col + d_col);
+            if in_bounds(r, c) {
+                table[sq] |= 1 << square_of(r, c);
+            }
+        }
+    }
+    table
+}
+
+fn ray_table(dir: usize) -> [u64; 64] {
+    let (d_row, d_col) = RAY_DELTAS[dir];
+    let mut table = [0u64; 64];
+    for sq in 0..64 {
+        let row = (sq / 8) as i8;
+        let col = (sq % 8) as i8;
+        let (mut r, mut c) = (row + d_row, col + d_col);
+        while in_bounds(r, c) {
+            table[sq] |= 1 << square_of(r, c);
+            r += d_row;
+            c += d_col;
+        }
+    }
+    table
+}
+
+fn pawn_table(color: Color) -> [u64; 64] {
+    let forward = color.forward();
+    let mut table = [0u64; 64];
+    for sq in 0..64 {
+        let row = (sq / 8) as i8;
+        let col = (sq % 8) as i8;
+        for d_col in [-1, 1] {
+            let (r, c) = (row + forward, col + d_col);
+            if in_bounds(r, c) {
+                table[sq] |= 1 << square_of(r, c);
+            }
+        }
+    }
+    table
+}
+
+fn build_tables() -> Tables {
+    let mut rays = [[0u64; 64]; 8];
+    for (dir, ray) in rays.iter_mut().enumerate() {
+        *ray = ray_table(dir);
+    }
+
+    Tables {
+        rays,
+        knight: jump_table(&KNIGHT_DELTAS),
+        king: jump_table(&KING_DELTAS),
+        pawn: [pawn_table(Color::White), pawn_table(Color::Black)],
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(build_tables)
+}
+
+// Attacks along a ray whose square indices increase as it walks away from the origin: the ray
+// is blocked at the nearest (lowest-index) occupied square, which is still included as a target.
+fn positive_ray_attacks(ray: u64, occupancy: u64) -> u64 {
+    let blockers = ray & occupancy;
+    if blockers == 0 {
+        return ray;
+    }
+    let nearest = blockers.trailing_zeros();
+    let up_to_and_including = if nearest == 63 {
+        u64::MAX
+    } else {
+        (1u64 << (nearest + 1)) - 1
+    };
+    ray & up_to_and_including
+}
+
+// Attacks along a ray whose square indices decrease as it walks away from the origin.
+fn negative_ray_attacks(ray: u64, occupancy: u64) -> u64 {
+    let blockers = ray & occupancy;
+    if blockers == 0 {
+        return ray;
+    }
+    let nearest = 63 - blockers.leading_zeros();
+    ray & (u64::MAX << nearest)
+}
+
+fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    let rays = &tables().rays;
+    positive_ray_attacks(rays[NORTH_EAST][square], occupancy)
+        | positive_ray_attacks(rays[NORTH_WEST][square], occupancy)
+        | negative_ray_attacks(rays[SOUTH_EAST][square], occupancy)
+        | negative_ray_attacks(rays[SOUTH_WEST][square], occupancy)
+}
+
+fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    let rays = &tables().rays;
+    positive_ray_attacks(rays[NORTH][square], occupancy)
+        | negative_ray_attacks(rays[SOUTH][square], occupancy)
+        | positive_ray_attacks(rays[EAST][square], occupancy)
+        | negative_ray_attacks(rays[WEST][square], occupancy)
+}
+
+// The set of squares a piece of `kind` and `color` standing on `square` attacks, given `occupancy`
+// (the blocker set for sliding pieces; ignored for knights and kings).
+pub fn attacks_from(kind: PieceKind, color: Color, square: usize, occupancy: u64) -> u64 {
+    match kind {
+        PieceKind::Pawn => tables().pawn[color.index()][square],
+        PieceKind::Knight => tables().knight[square],
+        PieceKind::Bishop => bishop_attacks(square, occupancy),
+        PieceKind::Rook => rook_attacks(square, occupancy),
+        PieceKind::Queen => bishop_attacks(square, occupancy) | rook_attacks(square, occupancy),
+        PieceKind::King => tables().king[square],
+    }
+}
@@ -1,5 +1,11 @@
 use std::io;
 
+mod attacks;
+mod search;
+mod zobrist;
+
+const SEARCH_DEPTH: u32 = 3;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Color {
     White,
@@ -20,9 +26,16 @@ impl Color {
             Color::Black => -1,
         }
     }
+
+    fn index(&self) -> usize {
+        match self {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum PieceKind {
     Pawn,
     Knight,
@@ -33,6 +46,26 @@ enum PieceKind {
 }
 
 impl PieceKind {
+    const ALL: [PieceKind; 6] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+    ];
+
+    fn index(&self) -> usize {
+        match self {
+            PieceKind::Pawn => 0,
+            PieceKind::Knight => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Rook => 3,
+            PieceKind::Queen => 4,
+            PieceKind::King => 5,
+        }
+    }
+
     fn icon(&self, color: &Color) -> String {
         String::from(match self {
             PieceKind::Pawn => match color {
@@ -97,12 +130,73 @@ impl Coord {
             col: self.col + other.col,
         }
     }
+
+    fn square(&self) -> usize {
+        (self.row * 8 + self.col) as usize
+    }
+
+    fn from_square(square: usize) -> Coord {
+        Coord {
+            row: (square / 8) as i8,
+            col: (square % 8) as i8,
+        }
+    }
+
+    fn to_algebraic(&self) -> String {
+        format!("{}{}", ('a' as u8 + self.col as u8) as char, self.row + 1)
+    }
+}
+
+// Iterates the set bits of a bitboard from least to most significant, yielding each as a Coord.
+struct BitboardIterator(u64);
+
+impl Iterator for BitboardIterator {
+    type Item = Coord;
+
+    fn next(&mut self) -> Option<Coord> {
+        if self.0 == 0 {
+            return None;
+        }
+        let lsb = self.0 & self.0.wrapping_neg();
+        self.0 &= !lsb;
+        Some(Coord::from_square(lsb.trailing_zeros() as usize))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CastleRookMove {
+    rook_start: Coord,
+    rook_end: Coord,
+}
+
+#[derive(Clone, Debug)]
+struct MoveRecord {
+    start: Coord,
+    end: Coord,
+    had_moved: bool,
+    captured_piece: Option<Piece>,
+    captured_square: Coord,
+    castle_rook: Option<CastleRookMove>,
+    promoted: bool,
+    prev_hash: u64,
+    prev_halfmove_clock: u32,
+    prev_fullmove_number: u32,
 }
 
 #[derive(Clone)]
 struct Board {
-    tiles: Vec<Vec<Option<Piece>>>,
+    // One occupancy mask per Color, and one per PieceKind (color-agnostic); a square's piece is
+    // whichever kind mask it appears in, intersected with whichever color mask it appears in.
+    color_occupancy: [u64; 2],
+    piece_occupancy: [u64; 6],
+    moved: u64,
     history: Vec<(Coord, Coord)>,
+    undo_stack: Vec<MoveRecord>,
+    hash: u64,
+    hash_history: Vec<u64>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    to_move: Color,
 }
 
 impl Board {
@@ -136,15 +230,23 @@ impl Board {
     }
 
     fn from_pieces(pieces: Vec<(Coord, Piece)>) -> Board {
-        let mut tiles: Vec<Vec<Option<Piece>>> =
-            (0..8).map(|_| (0..8).map(|_| None).collect()).collect();
-        for (pos, piece) in pieces {
-            tiles[pos.row as usize][pos.col as usize] = Some(piece);
-        }
-        let board = Board {
-            tiles,
+        let mut board = Board {
+            color_occupancy: [0; 2],
+            piece_occupancy: [0; 6],
+            moved: 0,
             history: Vec::new(),
+            undo_stack: Vec::new(),
+            hash: 0,
+            hash_history: Vec::new(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            to_move: Color::White,
         };
+        for (pos, piece) in pieces {
+            board.set(&pos, Some(piece));
+        }
+        board.hash = board.compute_hash();
+        board.hash_history.push(board.hash);
         board
     }
 
@@ -162,7 +264,7 @@ impl Board {
         piece_attributes.extend((0..8).map(|col| (1, col, PieceKind::Pawn, Color::White)));
         let dark_piece_attributes: Vec<(i8, i8, PieceKind, Color)> = piece_attributes
             .iter()
-            .map(|(row, col, kind, _)| (7 - *row, *col, kind.clone(), Color::Black))
+            .map(|(row, col, kind, _)| (7 - *row, *col, *kind, Color::Black))
             .collect();
         piece_attributes.extend(dark_piece_attributes);
         piece_attributes
@@ -171,15 +273,258 @@ impl Board {
             .collect()
     }
 
+    // Parses Forsyth-Edwards Notation into a Board. Returns None on malformed input rather than
+    // panicking, matching the style of `parse_move_string`.
+    fn from_fen(fen: &str) -> Option<Board> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 {
+            return None;
+        }
+        let placement = fields[0];
+        let active_color = fields[1];
+        let castling = fields[2];
+        let en_passant = fields[3];
+        let halfmove_clock = fields[4];
+        let fullmove_number = fields[5];
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+        for (rank_index, rank) in ranks.iter().enumerate() {
+            let row = 7 - rank_index as i8;
+            let mut col = 0i8;
+            for c in rank.chars() {
+                if let Some(empty_run) = c.to_digit(10) {
+                    col += empty_run as i8;
+                } else {
+                    if col >= 8 {
+                        return None;
+                    }
+                    let color = if c.is_uppercase() {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    let kind = match c.to_ascii_lowercase() {
+                        'p' => PieceKind::Pawn,
+                        'n' => PieceKind::Knight,
+                        'b' => PieceKind::Bishop,
+                        'r' => PieceKind::Rook,
+                        'q' => PieceKind::Queen,
+                        'k' => PieceKind::King,
+                        _ => return None,
+                    };
+                    let has_moved = match kind {
+                        PieceKind::Pawn => {
+                            let home_row = if color == Color::White { 1 } else { 6 };
+                            row != home_row
+                        }
+                        _ => true,
+                    };
+                    pieces.push((Coord { row, col }, Piece::new(kind, color, has_moved)));
+                    col += 1;
+                }
+            }
+            if col != 8 {
+                return None;
+            }
+        }
+
+        for color in [Color::White, Color::Black] {
+            let king_count = pieces
+                .iter()
+                .filter(|(_, piece)| piece.kind == PieceKind::King && piece.color == color)
+                .count();
+            if king_count != 1 {
+                return None;
+            }
+        }
+
+        let mut board = Board::from_pieces(pieces);
+
+        board.to_move = match active_color {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return None,
+        };
+
+        if castling != "-" {
+            for c in castling.chars() {
+                let (color, king_col, rook_col) = match c {
+                    'K' => (Color::White, 4, 7),
+                    'Q' => (Color::White, 4, 0),
+                    'k' => (Color::Black, 4, 7),
+                    'q' => (Color::Black, 4, 0),
+                    _ => return None,
+                };
+                let row = if color == Color::White { 0 } else { 7 };
+                if let Some(mut king) = board.get(&Coord { row, col: king_col }) {
+                    king.has_moved = false;
+                    board.set(&Coord { row, col: king_col }, Some(king));
+                }
+                if let Some(mut rook) = board.get(&Coord { row, col: rook_col }) {
+                    rook.has_moved = false;
+                    board.set(&Coord { row, col: rook_col }, Some(rook));
+                }
+            }
+        }
+
+        if en_passant != "-" {
+            let bytes = en_passant.as_bytes();
+            if bytes.len() != 2 {
+                return None;
+            }
+            let col = (bytes[0] as i8) - (b'a' as i8);
+            let row = (bytes[1] as i8) - (b'1' as i8);
+            let (start_row, end_row) = match row {
+                2 => (1, 3),
+                5 => (6, 4),
+                _ => return None,
+            };
+            if !(0..8).contains(&col) {
+                return None;
+            }
+            board.history.push((
+                Coord {
+                    row: start_row,
+                    col,
+                },
+                Coord { row: end_row, col },
+            ));
+        }
+
+        board.halfmove_clock = halfmove_clock.parse().ok()?;
+        board.fullmove_number = fullmove_number.parse().ok()?;
+        board.hash = board.compute_hash();
+        board.hash_history = vec![board.hash];
+
+        Some(board)
+    }
+
+    // Serializes the board to Forsyth-Edwards Notation, with `side` as the active color.
+    fn to_fen(&self, side: Color) -> String {
+        let mut placement = String::new();
+        for row in (0..8).rev() {
+            let mut empty_run = 0;
+            for col in 0..8 {
+                match self.get(&Coord { row, col }) {
+                    Some(piece) => {
+                        if empty_run > 0 {
+                            placement.push_str(&empty_run.to_string());
+                            empty_run = 0;
+                        }
+                        let letter = match piece.kind {
+                            PieceKind::Pawn => 'p',
+                            PieceKind::Knight => 'n',
+                            PieceKind::Bishop => 'b',
+                            PieceKind::Rook => 'r',
+                            PieceKind::Queen => 'q',
+                            PieceKind::King => 'k',
+                        };
+                        placement.push(if piece.color == Color::White {
+                            letter.to_ascii_uppercase()
+                        } else {
+                            letter
+                        });
+                    }
+                    None => empty_run += 1,
+                }
+            }
+            if empty_run > 0 {
+                placement.push_str(&empty_run.to_string());
+            }
+            if row > 0 {
+                placement.push('/');
+            }
+        }
+
+        let active_color = match side {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let mut castling = String::new();
+        if self.has_kingside_rights(Color::White) {
+            castling.push('K');
+        }
+        if self.has_queenside_rights(Color::White) {
+            castling.push('Q');
+        }
+        if self.has_kingside_rights(Color::Black) {
+            castling.push('k');
+        }
+        if self.has_queenside_rights(Color::Black) {
+            castling.push('q');
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.en_passant_file() {
+            Some(file) => {
+                let (start, end) = self.history.last().unwrap();
+                let target_row = (start.row + end.row) / 2;
+                format!("{}{}", (b'a' + file as u8) as char, target_row + 1)
+            }
+            None => String::from("-"),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement, active_color, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+
+    fn occupancy(&self) -> u64 {
+        self.color_occupancy[Color::White.index()] | self.color_occupancy[Color::Black.index()]
+    }
+
+    fn pieces(&self, kind: PieceKind, color: Color) -> u64 {
+        self.piece_occupancy[kind.index()] & self.color_occupancy[color.index()]
+    }
+
     fn get(&self, pos: &Coord) -> Option<Piece> {
-        self.tiles
-            .get(pos.row as usize)?
-            .get(pos.col as usize)?
-            .clone()
+        if !self.contains(pos) {
+            return None;
+        }
+        let mask = 1u64 << pos.square();
+
+        let color = if self.color_occupancy[Color::White.index()] & mask != 0 {
+            Color::White
+        } else if self.color_occupancy[Color::Black.index()] & mask != 0 {
+            Color::Black
+        } else {
+            return None;
+        };
+        let kind = *PieceKind::ALL
+            .iter()
+            .find(|kind| self.piece_occupancy[kind.index()] & mask != 0)?;
+        let has_moved = self.moved & mask != 0;
+
+        Some(Piece::new(kind, color, has_moved))
     }
 
     fn set(&mut self, pos: &Coord, piece: Option<Piece>) {
-        self.tiles[pos.row as usize][pos.col as usize] = piece;
+        let mask = 1u64 << pos.square();
+
+        for occupancy in self.color_occupancy.iter_mut() {
+            *occupancy &= !mask;
+        }
+        for occupancy in self.piece_occupancy.iter_mut() {
+            *occupancy &= !mask;
+        }
+        self.moved &= !mask;
+
+        if let Some(piece) = piece {
+            self.color_occupancy[piece.color.index()] |= mask;
+            self.piece_occupancy[piece.kind.index()] |= mask;
+            if piece.has_moved {
+                self.moved |= mask;
+            }
+        }
     }
 
     fn contains(&self, pos: &Coord) -> bool {
@@ -205,9 +550,11 @@ impl Board {
     }
 
     fn path_is_clear(&self, start: &Coord, end: &Coord) -> bool {
-        self.path_between(start, end)
+        let mask = self
+            .path_between(start, end)
             .iter()
-            .all(|pos| self.get(pos).is_none())
+            .fold(0u64, |mask, pos| mask | (1 << pos.square()));
+        self.occupancy() & mask == 0
     }
 
     fn follows_pawn_move_pattern(&self, start_piece: &Piece, start: &Coord, end: &Coord) -> bool {
@@ -233,6 +580,7 @@ impl Board {
             && match self.history.last() {
                 Some((prev_start, prev_end)) => {
                     end.col == prev_end.col
+                        && prev_end.row == start.row
                         && prev_start.col == prev_end.col
                         && prev_end.row == prev_start.row - forward * 2
                         && match self.get(prev_end) {
@@ -247,25 +595,6 @@ impl Board {
             }
     }
 
-    fn follows_knight_move_pattern(&self, start: &Coord, end: &Coord) -> bool {
-        let d_row = (start.row - end.row).abs();
-        let d_col = (start.col - end.col).abs();
-        d_row == 1 && d_col == 2 || d_row == 2 && d_col == 1
-    }
-
-    fn follows_bishop_move_pattern(&self, start: &Coord, end: &Coord) -> bool {
-        ((end.row - start.row).abs() == (end.col - start.col).abs())
-            && self.path_is_clear(start, end)
-    }
-
-    fn follows_rook_move_pattern(&self, start: &Coord, end: &Coord) -> bool {
-        ((end.row - start.row == 0) || (end.col - start.col == 0)) && self.path_is_clear(start, end)
-    }
-
-    fn follows_queen_move_pattern(&self, start: &Coord, end: &Coord) -> bool {
-        self.follows_bishop_move_pattern(start, end) || self.follows_rook_move_pattern(start, end)
-    }
-
     fn follows_king_move_pattern(&self, start: &Coord, end: &Coord) -> bool {
         let d_row = end.row - start.row;
         let d_col = end.col - start.col;
@@ -273,14 +602,24 @@ impl Board {
     }
 
     fn is_endangered(&self, side: &Color, pos: &Coord) -> bool {
-        for row in 0..8 {
-            for col in 0..8 {
-                if self.is_legal_move_no_check(&side.other(), &Coord { row, col }, pos) {
-                    return true;
-                }
-            }
-        }
-        false
+        let enemy = side.other();
+        let square = pos.square();
+        let occupancy = self.occupancy();
+
+        let knight_attackers =
+            attacks::attacks_from(PieceKind::Knight, enemy, square, occupancy)
+                & self.pieces(PieceKind::Knight, enemy);
+        let king_attackers = attacks::attacks_from(PieceKind::King, enemy, square, occupancy)
+            & self.pieces(PieceKind::King, enemy);
+        let diagonal_attackers = attacks::attacks_from(PieceKind::Bishop, enemy, square, occupancy)
+            & (self.pieces(PieceKind::Bishop, enemy) | self.pieces(PieceKind::Queen, enemy));
+        let straight_attackers = attacks::attacks_from(PieceKind::Rook, enemy, square, occupancy)
+            & (self.pieces(PieceKind::Rook, enemy) | self.pieces(PieceKind::Queen, enemy));
+        let pawn_attackers = attacks::attacks_from(PieceKind::Pawn, *side, square, occupancy)
+            & self.pieces(PieceKind::Pawn, enemy);
+
+        knight_attackers | king_attackers | diagonal_attackers | straight_attackers | pawn_attackers
+            != 0
     }
 
     fn is_castle(&self, piece: &Piece, start: &Coord, end: &Coord) -> bool {
@@ -306,8 +645,8 @@ impl Board {
             }
 
             let side = piece.color;
-            !self.is_endangered(&side, start)
-                && !self.is_endangered(&side, &rook_pos)
+            self.path_is_clear(start, &rook_pos)
+                && !self.is_endangered(&side, start)
                 && !self
                     .path_between(start, end)
                     .iter()
@@ -340,29 +679,26 @@ impl Board {
                 self.follows_pawn_move_pattern(&start_piece, start, end)
                     || self.is_en_passant(&start_piece, start, end)
             }
-            PieceKind::Knight => self.follows_knight_move_pattern(start, end),
-            PieceKind::Bishop => self.follows_bishop_move_pattern(start, end),
-            PieceKind::Rook => self.follows_rook_move_pattern(start, end),
-            PieceKind::Queen => self.follows_queen_move_pattern(start, end),
             PieceKind::King => {
                 self.follows_king_move_pattern(start, end)
                     || self.is_castle(&start_piece, start, end)
             }
+            kind => {
+                let attacked = attacks::attacks_from(
+                    kind,
+                    start_piece.color,
+                    start.square(),
+                    self.occupancy(),
+                );
+                attacked & (1 << end.square()) != 0
+            }
         }
     }
 
     fn find_king(&self, side: &Color) -> Coord {
-        // TODO can make this more efficient by saving piece mapping
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.get(&Coord { row, col }) {
-                    if piece.kind == PieceKind::King && piece.color == *side {
-                        return Coord { row, col };
-                    }
-                }
-            }
-        }
-        panic!("Could not find king on board")
+        BitboardIterator(self.pieces(PieceKind::King, *side))
+            .next()
+            .expect("Could not find king on board")
     }
 
     fn king_is_in_check(&self, side: &Color) -> bool {
@@ -370,116 +706,415 @@ impl Board {
         self.is_endangered(&side, &king_pos)
     }
 
-    fn is_legal_move(&self, side: &Color, start: &Coord, end: &Coord) -> bool {
+    fn side_to_move(&self) -> Color {
+        self.to_move
+    }
+
+    fn has_kingside_rights(&self, color: Color) -> bool {
+        let row = if color == Color::White { 0 } else { 7 };
+        match (self.get(&Coord { row, col: 4 }), self.get(&Coord { row, col: 7 })) {
+            (Some(king), Some(rook)) => {
+                king.kind == PieceKind::King
+                    && !king.has_moved
+                    && rook.kind == PieceKind::Rook
+                    && !rook.has_moved
+            }
+            _ => false,
+        }
+    }
+
+    fn has_queenside_rights(&self, color: Color) -> bool {
+        let row = if color == Color::White { 0 } else { 7 };
+        match (self.get(&Coord { row, col: 4 }), self.get(&Coord { row, col: 0 })) {
+            (Some(king), Some(rook)) => {
+                king.kind == PieceKind::King
+                    && !king.has_moved
+                    && rook.kind == PieceKind::Rook
+                    && !rook.has_moved
+            }
+            _ => false,
+        }
+    }
+
+    fn castling_rights_hash(&self) -> u64 {
+        let mut hash = 0;
+        if self.has_kingside_rights(Color::White) {
+            hash ^= zobrist::castling_key(zobrist::WHITE_KINGSIDE);
+        }
+        if self.has_queenside_rights(Color::White) {
+            hash ^= zobrist::castling_key(zobrist::WHITE_QUEENSIDE);
+        }
+        if self.has_kingside_rights(Color::Black) {
+            hash ^= zobrist::castling_key(zobrist::BLACK_KINGSIDE);
+        }
+        if self.has_queenside_rights(Color::Black) {
+            hash ^= zobrist::castling_key(zobrist::BLACK_QUEENSIDE);
+        }
+        hash
+    }
+
+    // The file a pawn could currently be captured on en passant, if any, per the last move made.
+    fn en_passant_file(&self) -> Option<i8> {
+        let (start, end) = self.history.last()?;
+        let piece = self.get(end)?;
+        if piece.kind == PieceKind::Pawn && (end.row - start.row).abs() == 2 {
+            Some(end.col)
+        } else {
+            None
+        }
+    }
+
+    fn en_passant_hash(&self) -> u64 {
+        self.en_passant_file()
+            .map_or(0, |file| zobrist::en_passant_key(file as usize))
+    }
+
+    fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for color in [Color::White, Color::Black] {
+            for &kind in PieceKind::ALL.iter() {
+                for square in BitboardIterator(self.pieces(kind, color)) {
+                    hash ^= zobrist::piece_square_key(kind, color, square.square());
+                }
+            }
+        }
+        if self.side_to_move() == Color::Black {
+            hash ^= zobrist::side_to_move_key();
+        }
+        hash ^= self.castling_rights_hash();
+        hash ^= self.en_passant_hash();
+        hash
+    }
+
+    fn is_threefold_repetition(&self) -> bool {
+        self.hash_history.iter().filter(|&&h| h == self.hash).count() >= 3
+    }
+
+    fn is_legal_move(&mut self, side: &Color, start: &Coord, end: &Coord, promotion: PieceKind) -> bool {
         self.is_legal_move_no_check(side, start, end) && {
-            let mut check_board = self.clone();
-            check_board.make_move(start, end);
-            !check_board.king_is_in_check(side)
+            self.make_move(start, end, promotion);
+            let in_check = self.king_is_in_check(side);
+            self.unmake_move();
+            !in_check
         }
     }
 
-    fn get_legal_moves(&self, side: &Color, start: &Coord) -> Vec<Coord> {
-        // TODO can make this more efficient by only checking pieces' move patterns
-        let mut legal_moves: Vec<Coord> = Vec::new();
-        for row in 0..8 {
-            for col in 0..8 {
-                let end = Coord { row, col };
-                if self.is_legal_move(side, start, &end) {
-                    legal_moves.push(end);
+    // The bitboard of squares a pawn on `start` could move or capture to, ignoring check.
+    fn pawn_targets(&self, piece: &Piece, start: &Coord) -> u64 {
+        let mut targets = 0u64;
+        let forward = piece.color.forward();
+
+        let one_step = Coord {
+            row: start.row + forward,
+            col: start.col,
+        };
+        if self.contains(&one_step) && self.get(&one_step).is_none() {
+            targets |= 1 << one_step.square();
+
+            let two_step = Coord {
+                row: start.row + forward * 2,
+                col: start.col,
+            };
+            if !piece.has_moved && self.contains(&two_step) && self.get(&two_step).is_none() {
+                targets |= 1 << two_step.square();
+            }
+        }
+
+        let capture_squares =
+            attacks::attacks_from(PieceKind::Pawn, piece.color, start.square(), self.occupancy());
+        for end in BitboardIterator(capture_squares) {
+            let is_capture = self.get(&end).map_or(false, |target| target.color != piece.color);
+            if is_capture || self.is_en_passant(piece, start, &end) {
+                targets |= 1 << end.square();
+            }
+        }
+
+        targets
+    }
+
+    // The bitboard of squares `start`'s piece could pattern-legally move or capture to (not
+    // accounting for whether the move leaves its own king in check).
+    fn pseudo_legal_targets(&self, side: &Color, start: &Coord) -> u64 {
+        let piece = match self.get(start) {
+            Some(piece) if piece.color == *side => piece,
+            _ => return 0,
+        };
+
+        match piece.kind {
+            PieceKind::Pawn => self.pawn_targets(&piece, start),
+            PieceKind::King => {
+                let mut targets = attacks::attacks_from(
+                    PieceKind::King,
+                    piece.color,
+                    start.square(),
+                    self.occupancy(),
+                ) & !self.color_occupancy[side.index()];
+
+                for end_col in [start.col - 2, start.col + 2] {
+                    let end = Coord {
+                        row: start.row,
+                        col: end_col,
+                    };
+                    if self.contains(&end) && self.is_castle(&piece, start, &end) {
+                        targets |= 1 << end.square();
+                    }
                 }
+                targets
+            }
+            kind => {
+                attacks::attacks_from(kind, piece.color, start.square(), self.occupancy())
+                    & !self.color_occupancy[side.index()]
+            }
+        }
+    }
+
+    fn get_legal_moves(&mut self, side: &Color, start: &Coord) -> Vec<Coord> {
+        // The promotion choice never affects whether a move is legal, so any piece kind
+        // works here; queen keeps this in line with parse_move_string's default.
+        let mut legal_moves: Vec<Coord> = Vec::new();
+        for end in BitboardIterator(self.pseudo_legal_targets(side, start)) {
+            self.make_move(start, &end, PieceKind::Queen);
+            let in_check = self.king_is_in_check(side);
+            self.unmake_move();
+            if !in_check {
+                legal_moves.push(end);
             }
         }
         legal_moves
     }
 
-    fn has_legal_moves(&self, side: &Color) -> bool {
-        for row in 0..8 {
-            for col in 0..8 {
-                if self.get_legal_moves(side, &Coord { row, col }).len() > 0 {
-                    return true;
-                }
+    fn has_legal_moves(&mut self, side: &Color) -> bool {
+        for start in BitboardIterator(self.color_occupancy[side.index()]) {
+            if self.get_legal_moves(side, &start).len() > 0 {
+                return true;
             }
         }
         false
     }
 
     fn undo_move(&mut self) {
-        let mut board = Board::new();
-        board.make_moves(&self.history[..self.history.len() - 1]);
-        self.tiles = board.tiles;
-        self.history = board.history;
-    }
-
-    fn make_move(&mut self, start: &Coord, end: &Coord) {
-        if let Some(mut piece) = self.get(start) {
-            match piece.kind {
-                PieceKind::Pawn => {
-                    if self.is_en_passant(&piece, start, end) {
-                        self.set(
-                            &Coord {
-                                row: start.row,
-                                col: end.col,
-                            },
-                            None,
-                        );
-                    }
-                    if end.row == 7 || end.row == 0 {
-                        self.set(end, Some(Piece::new(PieceKind::Queen, piece.color, true)));
-                    }
+        self.unmake_move();
+    }
+
+    // Move candidates for `start` -> `end`, expanded to one entry per underpromotion choice
+    // when the move is a pawn reaching the back rank, or a single Queen-tagged entry otherwise
+    // (the promotion kind is ignored by make_move/unmake_move for non-promoting moves).
+    fn perft_promotions(&self, piece: &Piece, end: &Coord) -> Vec<PieceKind> {
+        if piece.kind == PieceKind::Pawn && (end.row == 7 || end.row == 0) {
+            vec![
+                PieceKind::Queen,
+                PieceKind::Rook,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+            ]
+        } else {
+            vec![PieceKind::Queen]
+        }
+    }
+
+    // Counts the leaf nodes reachable in `depth` plies by exhaustively making and unmaking every
+    // legal move for `side`. Used to validate move generation against known reference counts.
+    fn perft(&mut self, side: Color, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut nodes = 0;
+        for start in BitboardIterator(self.color_occupancy[side.index()]) {
+            let piece = self.get(&start).expect("occupancy bit should have a piece");
+            for end in self.get_legal_moves(&side, &start) {
+                for promotion in self.perft_promotions(&piece, &end) {
+                    self.make_move(&start, &end, promotion);
+                    nodes += self.perft(side.other(), depth - 1);
+                    self.unmake_move();
                 }
-                PieceKind::King => {
-                    if end.col - start.col > 1 {
-                        self.set(
-                            &Coord {
-                                row: start.row,
-                                col: end.col - 1,
-                            },
-                            Some(Piece::new(PieceKind::Rook, piece.color, true)),
-                        );
-                        self.set(
-                            &Coord {
-                                row: start.row,
-                                col: start.col + 3,
-                            },
-                            None,
-                        );
-                    } else if end.col - start.col < -1 {
-                        self.set(
-                            &Coord {
-                                row: start.row,
-                                col: end.col + 1,
-                            },
-                            Some(Piece::new(PieceKind::Rook, piece.color, true)),
-                        );
-                        self.set(
-                            &Coord {
-                                row: start.row,
-                                col: start.col - 4,
-                            },
-                            None,
-                        );
-                    }
+            }
+        }
+        nodes
+    }
+
+    // Like `perft`, but prints the node count contributed by each root move before returning
+    // the total; useful for narrowing down which move a move-generation bug hides behind.
+    fn perft_divide(&mut self, side: Color, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut total = 0;
+        for start in BitboardIterator(self.color_occupancy[side.index()]) {
+            let piece = self.get(&start).expect("occupancy bit should have a piece");
+            for end in self.get_legal_moves(&side, &start) {
+                for promotion in self.perft_promotions(&piece, &end) {
+                    self.make_move(&start, &end, promotion);
+                    let nodes = self.perft(side.other(), depth - 1);
+                    self.unmake_move();
+                    println!("{}{}: {}", start.to_algebraic(), end.to_algebraic(), nodes);
+                    total += nodes;
                 }
-                _ => {}
             }
+        }
+        println!("Total: {}", total);
+        total
+    }
+
+    // Sets `pos`, keeping `self.hash` in sync by XORing out whatever piece-square key previously
+    // occupied the square and XORing in the key for whatever now does.
+    fn set_hashed(&mut self, pos: &Coord, piece: Option<Piece>) {
+        if let Some(old) = self.get(pos) {
+            self.hash ^= zobrist::piece_square_key(old.kind, old.color, pos.square());
+        }
+        self.set(pos, piece.clone());
+        if let Some(new_piece) = piece {
+            self.hash ^= zobrist::piece_square_key(new_piece.kind, new_piece.color, pos.square());
+        }
+    }
+
+    fn make_move(&mut self, start: &Coord, end: &Coord, promotion: PieceKind) {
+        let mut piece = match self.get(start) {
+            Some(piece) => piece,
+            None => return,
+        };
 
-            piece.has_moved = true;
-            self.set(end, Some(piece));
-            self.set(start, None);
+        let had_moved = piece.has_moved;
+        let moved_kind = piece.kind;
+        let mut captured_square = end.clone();
+        let mut captured_piece = self.get(end);
+        let mut castle_rook = None;
+        let mut promoted = false;
 
-            self.history.push((start.clone(), end.clone()))
+        let prev_hash = self.hash;
+        let prev_halfmove_clock = self.halfmove_clock;
+        let prev_fullmove_number = self.fullmove_number;
+        let prev_castling_hash = self.castling_rights_hash();
+        let prev_en_passant_hash = self.en_passant_hash();
+
+        match piece.kind {
+            PieceKind::Pawn => {
+                if self.is_en_passant(&piece, start, end) {
+                    captured_square = Coord {
+                        row: start.row,
+                        col: end.col,
+                    };
+                    captured_piece = self.get(&captured_square);
+                    self.set_hashed(&captured_square, None);
+                }
+                if end.row == 7 || end.row == 0 {
+                    promoted = true;
+                    piece = Piece::new(promotion, piece.color, true);
+                }
+            }
+            PieceKind::King => {
+                if end.col - start.col > 1 {
+                    let rook_start = Coord {
+                        row: start.row,
+                        col: start.col + 3,
+                    };
+                    let rook_end = Coord {
+                        row: start.row,
+                        col: end.col - 1,
+                    };
+                    self.set_hashed(&rook_end, Some(Piece::new(PieceKind::Rook, piece.color, true)));
+                    self.set_hashed(&rook_start, None);
+                    castle_rook = Some(CastleRookMove {
+                        rook_start,
+                        rook_end,
+                    });
+                } else if end.col - start.col < -1 {
+                    let rook_start = Coord {
+                        row: start.row,
+                        col: start.col - 4,
+                    };
+                    let rook_end = Coord {
+                        row: start.row,
+                        col: end.col + 1,
+                    };
+                    self.set_hashed(&rook_end, Some(Piece::new(PieceKind::Rook, piece.color, true)));
+                    self.set_hashed(&rook_start, None);
+                    castle_rook = Some(CastleRookMove {
+                        rook_start,
+                        rook_end,
+                    });
+                }
+            }
+            _ => {}
         }
+
+        piece.has_moved = true;
+        self.set_hashed(end, Some(piece));
+        self.set_hashed(start, None);
+
+        self.history.push((start.clone(), end.clone()));
+        self.halfmove_clock = if moved_kind == PieceKind::Pawn || captured_piece.is_some() {
+            0
+        } else {
+            prev_halfmove_clock + 1
+        };
+
+        if self.to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.hash ^= zobrist::side_to_move_key();
+        self.hash ^= prev_castling_hash;
+        self.hash ^= self.castling_rights_hash();
+        self.hash ^= prev_en_passant_hash;
+        self.hash ^= self.en_passant_hash();
+        self.hash_history.push(self.hash);
+        self.to_move = self.to_move.other();
+
+        self.undo_stack.push(MoveRecord {
+            start: start.clone(),
+            end: end.clone(),
+            had_moved,
+            captured_piece,
+            captured_square,
+            castle_rook,
+            promoted,
+            prev_hash,
+            prev_halfmove_clock,
+            prev_fullmove_number,
+        });
     }
 
-    fn make_moves(&mut self, moves: &[(Coord, Coord)]) {
-        for (start, end) in moves {
-            self.make_move(start, end);
+    fn unmake_move(&mut self) {
+        let record = match self.undo_stack.pop() {
+            Some(record) => record,
+            None => return,
+        };
+        self.history.pop();
+        self.hash_history.pop();
+
+        let mut piece = self
+            .get(&record.end)
+            .expect("move record end square should be occupied");
+        if record.promoted {
+            piece.kind = PieceKind::Pawn;
         }
+        piece.has_moved = record.had_moved;
+        self.set(&record.start, Some(piece));
+        self.set(&record.end, None);
+        self.set(&record.captured_square, record.captured_piece);
+
+        if let Some(castle) = record.castle_rook {
+            let mut rook = self
+                .get(&castle.rook_end)
+                .expect("rook should be at castle destination");
+            rook.has_moved = false;
+            self.set(&castle.rook_start, Some(rook));
+            self.set(&castle.rook_end, None);
+        }
+
+        self.hash = record.prev_hash;
+        self.halfmove_clock = record.prev_halfmove_clock;
+        self.fullmove_number = record.prev_fullmove_number;
+        self.to_move = self.to_move.other();
     }
 
-    fn parse_move_string(move_string: &str) -> Option<(Coord, Coord)> {
+    // Parses long-algebraic moves like "e2 e4", optionally followed by a promotion letter
+    // ("e7 e8 q", "e7 e8 n") for underpromotion. Defaults to queen when the suffix is absent.
+    fn parse_move_string(move_string: &str) -> Option<(Coord, Coord, PieceKind)> {
         let split: Vec<&str> = move_string.trim().split_whitespace().collect();
-        if split.len() != 2 {
+        if split.len() != 2 && split.len() != 3 {
             return None;
         }
 
@@ -501,6 +1136,14 @@ impl Board {
             return None;
         }
 
+        let promotion = match split.get(2) {
+            Some(&"q") | None => PieceKind::Queen,
+            Some(&"r") => PieceKind::Rook,
+            Some(&"b") => PieceKind::Bishop,
+            Some(&"n") => PieceKind::Knight,
+            Some(_) => return None,
+        };
+
         Some((
             Coord {
                 row: start_row as i8,
@@ -510,30 +1153,50 @@ impl Board {
                 row: end_row as i8,
                 col: end_col as i8,
             },
+            promotion,
         ))
     }
 
-    fn play(&mut self) {
+    fn play(&mut self, vs_computer: bool) {
         let mut side = Color::White;
         loop {
             self.print(side);
-            println!("Enter a move:");
 
-            let mut move_string = String::new();
+            let option_move = if vs_computer && side == Color::Black {
+                println!("Computer is thinking...");
+                self.best_move(side, SEARCH_DEPTH)
+                    .map(|(start, end)| (start, end, PieceKind::Queen))
+            } else {
+                println!("Enter a move:");
 
-            io::stdin()
-                .read_line(&mut move_string)
-                .expect("Failed to read move");
+                let mut move_string = String::new();
 
-            let option_move = Board::parse_move_string(&move_string);
+                io::stdin()
+                    .read_line(&mut move_string)
+                    .expect("Failed to read move");
+
+                Board::parse_move_string(&move_string)
+            };
 
-            if let Some((start, end)) = option_move {
-                if self.is_legal_move(&side, &start, &end) {
-                    self.make_move(&start, &end);
+            if let Some((start, end, promotion)) = option_move {
+                if self.is_legal_move(&side, &start, &end, promotion) {
+                    self.make_move(&start, &end, promotion);
                     side = side.other();
                 }
             }
 
+            if self.is_threefold_repetition() {
+                self.print(side);
+                println!("Draw by threefold repetition!");
+                break;
+            }
+
+            if self.halfmove_clock >= 100 {
+                self.print(side);
+                println!("Draw by the fifty-move rule!");
+                break;
+            }
+
             if !self.has_legal_moves(&side) {
                 self.print(side);
                 if self.king_is_in_check(&side) {
@@ -549,5 +1212,78 @@ impl Board {
 }
 
 fn main() {
-    Board::new().play();
+    let args: Vec<String> = std::env::args().collect();
+    let fen = args
+        .iter()
+        .position(|arg| arg == "--fen")
+        .and_then(|index| args.get(index + 1));
+
+    let mut board = match fen {
+        Some(fen) => Board::from_fen(fen).unwrap_or_else(|| {
+            eprintln!("Invalid FEN, starting from the default position");
+            Board::new()
+        }),
+        None => Board::new(),
+    };
+
+    let perft_depth = args
+        .iter()
+        .position(|arg| arg == "--perft")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|depth| depth.parse::<u32>().ok());
+
+    if let Some(depth) = perft_depth {
+        let side = board.to_move;
+        board.perft_divide(side, depth);
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--to-fen") {
+        println!("{}", board.to_fen(board.to_move));
+        return;
+    }
+
+    board.play(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference counts from the chess programming wiki's perft results page.
+    #[test]
+    fn perft_matches_known_counts() {
+        let mut board = Board::new();
+        assert_eq!(board.perft(Color::White, 1), 20);
+        assert_eq!(board.perft(Color::White, 2), 400);
+        assert_eq!(board.perft(Color::White, 3), 8902);
+    }
+
+    // Kiwipete: a position dense with captures, checks, castling rights, and en passant, which
+    // catches bugs (like illegal castling) that the start position can't.
+    #[test]
+    fn perft_matches_known_counts_kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).expect("valid FEN");
+        assert_eq!(board.perft(Color::White, 1), 48);
+        assert_eq!(board.perft(Color::White, 2), 2039);
+        assert_eq!(board.perft(Color::White, 3), 97862);
+    }
+
+    // from_fen/to_fen should round-trip, including fields like the fullmove number that aren't
+    // derivable from the board position itself.
+    #[test]
+    fn fen_round_trips() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 3 17";
+        let board = Board::from_fen(fen).expect("valid FEN");
+        assert_eq!(board.to_fen(Color::White), fen);
+    }
+
+    // A position with no king at all (or two) is malformed, not just unusual; from_fen must
+    // reject it rather than return a Board that later panics in find_king.
+    #[test]
+    fn from_fen_rejects_positions_without_exactly_one_king_per_side() {
+        assert!(Board::from_fen("8/8/8/8/8/4P3/8/8 w - - 0 1").is_none());
+        assert!(Board::from_fen("k7/8/8/8/8/4P3/8/KK6 w - - 0 1").is_none());
+    }
 }
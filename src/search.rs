@@ -0,0 +1,113 @@
+use crate::{Board, Color, Coord, PieceKind};
+
+fn piece_value(kind: &PieceKind) -> f32 {
+    match kind {
+        PieceKind::Pawn => 1.0,
+        PieceKind::Knight => 3.0,
+        PieceKind::Bishop => 3.0,
+        PieceKind::Rook => 5.0,
+        PieceKind::Queen => 9.0,
+        PieceKind::King => 1000.0,
+    }
+}
+
+impl Board {
+    // Material balance plus a small mobility term, from `side`'s perspective.
+    fn evaluate(&mut self, side: &Color) -> f32 {
+        let mut score = 0.0;
+        for row in 0..8 {
+            for col in 0..8 {
+                if let Some(piece) = self.get(&Coord { row, col }) {
+                    let value = piece_value(&piece.kind);
+                    score += if piece.color == *side { value } else { -value };
+                }
+            }
+        }
+
+        let mobility = self.get_all_legal_moves(side).len() as f32
+            - self.get_all_legal_moves(&side.other()).len() as f32;
+        score + 0.1 * mobility
+    }
+
+    fn get_all_legal_moves(&mut self, side: &Color) -> Vec<(Coord, Coord)> {
+        let mut moves = Vec::new();
+        for row in 0..8 {
+            for col in 0..8 {
+                let start = Coord { row, col };
+                if self.get(&start).map_or(false, |piece| piece.color == *side) {
+                    for end in self.get_legal_moves(side, &start) {
+                        moves.push((start.clone(), end));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    // Negamax search with alpha-beta pruning over `side`'s legal moves, returning the best
+    // score (from `side`'s perspective) and the move that achieves it.
+    fn negamax(
+        &mut self,
+        side: &Color,
+        mut alpha: f32,
+        beta: f32,
+        depth: u32,
+    ) -> (f32, Option<(Coord, Coord)>) {
+        let moves = self.get_all_legal_moves(side);
+
+        if depth == 0 || moves.is_empty() {
+            let score = if !moves.is_empty() {
+                self.evaluate(side)
+            } else if self.king_is_in_check(side) {
+                -1000.0
+            } else {
+                0.0
+            };
+            return (score, None);
+        }
+
+        let mut best_score = f32::NEG_INFINITY;
+        let mut best_move = None;
+
+        for (start, end) in moves {
+            self.make_move(&start, &end, PieceKind::Queen);
+            let (score, _) = self.negamax(&side.other(), -beta, -alpha, depth - 1);
+            let score = -score;
+            self.unmake_move();
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some((start, end));
+            }
+            if best_score > alpha {
+                alpha = best_score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        (best_score, best_move)
+    }
+
+    // Picks a move for `side` by searching `depth` plies ahead.
+    pub fn best_move(&mut self, side: Color, depth: u32) -> Option<(Coord, Coord)> {
+        let (_, best_move) = self.negamax(&side, f32::NEG_INFINITY, f32::INFINITY, depth);
+        best_move
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stalemate (no legal moves, king not in check) is a draw, not a loss or an evaluated leaf.
+    #[test]
+    fn stalemate_scores_as_a_draw() {
+        let mut board = Board::from_fen("k7/2K5/1Q6/8/8/8/8/8 b - - 0 1").expect("valid FEN");
+        let (score, best_move) =
+            board.negamax(&Color::Black, f32::NEG_INFINITY, f32::INFINITY, 0);
+        assert_eq!(score, 0.0);
+        assert_eq!(best_move, None);
+    }
+}
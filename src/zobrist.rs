@@ -0,0 +1,83 @@
+use std::sync::OnceLock;
+
+use crate::{Color, PieceKind};
+
+pub const WHITE_KINGSIDE: usize = 0;
+pub const WHITE_QUEENSIDE: usize = 1;
+pub const BLACK_KINGSIDE: usize = 2;
+pub const BLACK_QUEENSIDE: usize = 3;
+
+// A fixed seed keeps hashes reproducible across runs.
+const SEED: u64 = 0x9E3779B97F4A7C15;
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+struct Tables {
+    piece_square: [[[u64; 64]; 6]; 2],
+    side_to_move: u64,
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+fn build_tables() -> Tables {
+    let mut rng = SplitMix64(SEED);
+
+    let mut piece_square = [[[0u64; 64]; 6]; 2];
+    for color in piece_square.iter_mut() {
+        for kind in color.iter_mut() {
+            for key in kind.iter_mut() {
+                *key = rng.next();
+            }
+        }
+    }
+
+    let side_to_move = rng.next();
+
+    let mut castling = [0u64; 4];
+    for key in castling.iter_mut() {
+        *key = rng.next();
+    }
+
+    let mut en_passant_file = [0u64; 8];
+    for key in en_passant_file.iter_mut() {
+        *key = rng.next();
+    }
+
+    Tables {
+        piece_square,
+        side_to_move,
+        castling,
+        en_passant_file,
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(build_tables)
+}
+
+pub fn piece_square_key(kind: PieceKind, color: Color, square: usize) -> u64 {
+    tables().piece_square[color.index()][kind.index()][square]
+}
+
+pub fn side_to_move_key() -> u64 {
+    tables().side_to_move
+}
+
+pub fn castling_key(right: usize) -> u64 {
+    tables().castling[right]
+}
+
+pub fn en_passant_key(file: usize) -> u64 {
+    tables().en_passant_file[file]
+}